@@ -0,0 +1,41 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hyper::{Body, Response};
+use percent_encoding::percent_decode_str;
+use rquickjs::{Ctx, Exception, Result};
+
+use crate::util::ResultExt;
+
+pub(crate) const DEFAULT_MEDIA_TYPE: &str = "text/plain;charset=US-ASCII";
+
+/// Decodes a `data:` URL's media type and payload (base64 or
+/// percent-encoded) and wraps it directly in a 200 response, without
+/// going through the HTTP client.
+pub(crate) fn build_response(ctx: &Ctx<'_>, url: &str) -> Result<Response<Body>> {
+    let rest = url
+        .strip_prefix("data:")
+        .ok_or_else(|| Exception::throw_type(ctx, "Not a data: URL"))?;
+
+    let (meta, data) = rest
+        .split_once(',')
+        .ok_or_else(|| Exception::throw_type(ctx, "Malformed data: URL: missing comma"))?;
+
+    let is_base64 = meta.ends_with(";base64");
+    let media_type = meta.strip_suffix(";base64").unwrap_or(meta);
+    let media_type = if media_type.is_empty() {
+        DEFAULT_MEDIA_TYPE
+    } else {
+        media_type
+    };
+
+    let bytes = if is_base64 {
+        BASE64.decode(data).or_throw(ctx)?
+    } else {
+        percent_decode_str(data).collect()
+    };
+
+    Response::builder()
+        .status(200)
+        .header("content-type", media_type)
+        .body(Body::from(bytes))
+        .or_throw(ctx)
+}
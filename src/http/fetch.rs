@@ -1,11 +1,111 @@
 use hyper::{Body, Client as HttpClient, Request, Uri};
 use rquickjs::{
     function::Opt,
-    prelude::{Async, Func},
+    prelude::{Async, Func, This},
     Ctx, Error, Exception, Object, Result, Value,
 };
 use rustls::{ClientConfig, OwnedTrustAnchor, RootCertStore};
-use std::time::Instant;
+use std::{env, sync::Arc, time::Duration, time::Instant};
+use tokio::sync::Notify;
+
+const MAX_REDIRECTS: u8 = 20;
+
+/// Registers an `abort` listener on `signal` and returns a handle that
+/// removes it again once dropped, so a signal reused across several
+/// `fetch()` calls doesn't accumulate one listener per call.
+fn register_abort_notify<'js>(ctx: &Ctx<'js>, signal: &Object<'js>) -> Result<Arc<AbortState<'js>>> {
+    let notify = Arc::new(Notify::new());
+    let on_abort = notify.clone();
+    let listener = rquickjs::Function::new(ctx.clone(), move || on_abort.notify_waiters())?;
+
+    let add_event_listener: rquickjs::Function = signal.get("addEventListener")?;
+    add_event_listener.call::<_, ()>((This(signal.clone()), "abort", listener.clone()))?;
+
+    Ok(Arc::new(AbortState::new(notify, signal.clone(), listener)))
+}
+
+fn net_timeout_from_env() -> Option<u64> {
+    env::var("LLRT_NET_TIMEOUT")
+        .ok()
+        .and_then(|value| value.parse().ok())
+}
+
+fn read_certs(ctx: &Ctx<'_>, env_var: &str, path: &str) -> Result<Vec<rustls::Certificate>> {
+    let file = std::fs::File::open(path).or_throw_msg(ctx, &format!(
+        "\"{env_var}\" points to a file that cannot be opened: {path}"
+    ))?;
+    let mut reader = std::io::BufReader::new(file);
+    rustls_pemfile::certs(&mut reader)
+        .or_throw_msg(ctx, &format!("\"{env_var}\" does not contain a valid PEM certificate: {path}"))
+        .map(|certs| certs.into_iter().map(rustls::Certificate).collect())
+}
+
+fn read_private_key(ctx: &Ctx<'_>, env_var: &str, path: &str) -> Result<rustls::PrivateKey> {
+    let read = |parser: fn(&mut dyn std::io::BufRead) -> std::io::Result<Vec<Vec<u8>>>| -> Result<Vec<Vec<u8>>> {
+        let file = std::fs::File::open(path).or_throw_msg(ctx, &format!(
+            "\"{env_var}\" points to a file that cannot be opened: {path}"
+        ))?;
+        let mut reader = std::io::BufReader::new(file);
+        parser(&mut reader).or_throw_msg(ctx, &format!("\"{env_var}\" does not contain a valid private key: {path}"))
+    };
+
+    // Client keys are commonly PKCS8 ("BEGIN PRIVATE KEY") or, less often,
+    // PKCS1/SEC1 ("BEGIN RSA/EC PRIVATE KEY"); accept either.
+    let key = read(rustls_pemfile::pkcs8_private_keys)?
+        .into_iter()
+        .next()
+        .or(read(rustls_pemfile::rsa_private_keys)?.into_iter().next())
+        .or(read(rustls_pemfile::ec_private_keys)?.into_iter().next());
+
+    key.map(rustls::PrivateKey)
+        .ok_or_else(|| Exception::throw_type(ctx, &format!("\"{env_var}\" does not contain a private key: {path}")))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum RedirectMode {
+    Follow,
+    Manual,
+    Error,
+}
+
+impl RedirectMode {
+    fn parse(ctx: &Ctx<'_>, value: Option<&str>) -> Result<Self> {
+        match value {
+            None | Some("follow") => Ok(Self::Follow),
+            Some("manual") => Ok(Self::Manual),
+            Some("error") => Ok(Self::Error),
+            Some(other) => Err(Exception::throw_type(
+                ctx,
+                &format!("Invalid redirect mode: {}", other),
+            )),
+        }
+    }
+}
+
+fn is_same_origin(a: &Uri, b: &Uri) -> bool {
+    a.scheme_str() == b.scheme_str() && a.authority() == b.authority()
+}
+
+fn resolve_location(base: &Uri, location: &str) -> Option<Uri> {
+    // A network-path reference ("//host/path") parses with `authority() ==
+    // None` since `Uri` doesn't special-case the leading "//", so it has to
+    // be detected on the raw string before falling through to the relative
+    // case below (which would otherwise keep the *original* authority).
+    if let Some(rest) = location.strip_prefix("//") {
+        let scheme = base.scheme_str().unwrap_or("http");
+        return format!("{scheme}://{rest}").parse().ok();
+    }
+
+    let location: Uri = location.parse().ok()?;
+    if location.authority().is_some() {
+        return Some(location);
+    }
+
+    let mut parts = location.into_parts();
+    parts.scheme = base.scheme().cloned();
+    parts.authority = base.authority().cloned();
+    Uri::from_parts(parts).ok()
+}
 
 use webpki::TrustAnchor;
 use webpki_roots::TLS_SERVER_ROOTS;
@@ -17,7 +117,10 @@ use crate::{
 };
 use crate::{security::HTTP_ALLOW_LIST, VERSION};
 
-use super::response::{Response, ResponseData};
+use super::data_url;
+use super::proxy::ProxyConnector;
+use super::response::{AbortState, Cancellation, Response, ResponseData};
+use super::uds;
 
 struct FetchArgs<'js>(Ctx<'js>, Value<'js>, Opt<Value<'js>>);
 
@@ -53,21 +156,54 @@ pub(crate) fn init(ctx: &Ctx<'_>, globals: &Object) -> Result<()> {
     root_certificates
         .add_server_trust_anchors(TLS_SERVER_ROOTS.0.iter().map(create_owned_trust_anchor));
 
-    let tls = ClientConfig::builder()
+    if let Ok(ca_bundle) = env::var("LLRT_CA_BUNDLE") {
+        let certs = read_certs(ctx, "LLRT_CA_BUNDLE", &ca_bundle)?;
+        for cert in &certs {
+            root_certificates.add(cert).or_throw_msg(
+                ctx,
+                &format!("\"LLRT_CA_BUNDLE\" contains an invalid certificate: {ca_bundle}"),
+            )?;
+        }
+    }
+
+    let tls_builder = ClientConfig::builder()
         .with_safe_defaults()
         //.with_native_roots()
-        .with_root_certificates(root_certificates)
-        .with_no_client_auth();
+        .with_root_certificates(root_certificates);
+
+    let non_empty_env = |var: &str| env::var(var).ok().filter(|v| !v.is_empty());
+    let client_cert = non_empty_env("LLRT_CLIENT_CERT");
+    let client_key = non_empty_env("LLRT_CLIENT_KEY");
+
+    let tls = match (client_cert, client_key) {
+        (Some(cert_path), Some(key_path)) => {
+            let certs = read_certs(ctx, "LLRT_CLIENT_CERT", &cert_path)?;
+            let key = read_private_key(ctx, "LLRT_CLIENT_KEY", &key_path)?;
+            tls_builder
+                .with_client_auth_cert(certs, key)
+                .or_throw_msg(ctx, "Invalid mTLS client certificate/key pair")?
+        }
+        (None, None) => tls_builder.with_no_client_auth(),
+        _ => {
+            return Err(Exception::throw_reference(
+                ctx,
+                "\"LLRT_CLIENT_CERT\" and \"LLRT_CLIENT_KEY\" must be set together",
+            ));
+        }
+    };
 
     let https = hyper_rustls::HttpsConnectorBuilder::new()
-        .with_tls_config(tls)
+        .with_tls_config(tls.clone())
         .https_or_http()
         .enable_http1()
+        .enable_http2()
         .build();
 
+    let proxy = ProxyConnector::new(https, tls);
+
     let client = HttpClient::builder()
         .pool_idle_timeout(None)
-        .build::<_, hyper::Body>(https);
+        .build::<_, hyper::Body>(proxy);
 
     globals.set(
         "fetch",
@@ -77,8 +213,12 @@ pub(crate) fn init(ctx: &Ctx<'_>, globals: &Object) -> Result<()> {
             let client = client.clone();
 
             let mut method = Ok(hyper::Method::GET);
-            let mut body: Result<Body> = Ok(Body::empty());
+            let mut body_bytes: Result<Vec<u8>> = Ok(Vec::new());
             let mut headers: Option<Result<Headers>> = None;
+            let mut redirect = Ok(RedirectMode::Follow);
+            let mut signal: Option<Object> = None;
+            let mut timeout_ms = net_timeout_from_env();
+            let mut unix_socket_opt: Option<String> = None;
 
             let (url, resource_options) = get_url_options(resource);
             let mut url = url;
@@ -102,14 +242,23 @@ pub(crate) fn init(ctx: &Ctx<'_>, globals: &Object) -> Result<()> {
 
                 let body_opt: Option<Value> = opts.get("body").unwrap_or_default();
                 let url_opt: Option<String> = opts.get("url").unwrap_or_default();
+                let redirect_opts = opts.get_optional::<&str, String>("redirect");
+                signal = opts.get_optional("signal").ok().flatten();
+                unix_socket_opt = opts
+                    .get_optional::<&str, String>("socketPath")
+                    .ok()
+                    .flatten()
+                    .or_else(|| opts.get_optional::<&str, String>("unixSocket").ok().flatten());
+                if let Some(opt_timeout) = opts.get_optional::<&str, u64>("timeout").ok().flatten() {
+                    timeout_ms = Some(opt_timeout);
+                }
 
                 if let Some(url_val) = url_opt {
                     url = Some(Ok(url_val));
                 }
 
                 if let Some(body_value) = body_opt {
-                    let bytes = get_bytes(&ctx, body_value);
-                    body = bytes.map(Body::from);
+                    body_bytes = get_bytes(&ctx, body_value);
                 }
 
                 method = method_opts.and_then(|m| {
@@ -128,6 +277,8 @@ pub(crate) fn init(ctx: &Ctx<'_>, globals: &Object) -> Result<()> {
                         )),
                     }
                 });
+
+                redirect = redirect_opts.and_then(|r| RedirectMode::parse(&ctx, r.as_deref()));
             }
 
             async move {
@@ -135,32 +286,149 @@ pub(crate) fn init(ctx: &Ctx<'_>, globals: &Object) -> Result<()> {
                     Err(Exception::throw_reference(&ctx, "Missing required url"))
                 })?;
 
-                let uri: Uri = url.parse().or_throw(&ctx)?;
-
-                let method = method?;
+                let mut method = method?;
                 let method_string = method.to_string();
+                let redirect = redirect?;
 
-                ensure_url_access(&ctx, &uri)?;
+                let mut headers = headers.transpose()?.unwrap_or_default();
+                let body_bytes = body_bytes?;
 
-                let mut req = Request::builder()
-                    .method(method)
-                    .uri(uri)
-                    .header("user-agent", format!("llrt {}", VERSION))
-                    .header("accept", "*/*");
+                if let Some(signal) = &signal {
+                    if signal.get::<_, bool>("aborted").unwrap_or(false) {
+                        return Err(Exception::throw_message(&ctx, "The operation was aborted"));
+                    }
+                }
+                let abort_notify = signal
+                    .as_ref()
+                    .map(|signal| register_abort_notify(&ctx, signal))
+                    .transpose()?;
+                let deadline = timeout_ms
+                    .map(|ms| tokio::time::Instant::now() + Duration::from_millis(ms));
+                let cancellation = Cancellation {
+                    abort: abort_notify,
+                    deadline,
+                };
+
+                if url.starts_with("data:") {
+                    let res = data_url::build_response(&ctx, &url)?;
+                    return Ok(Response {
+                        data: ResponseData::new(ctx, res, method_string, url, false, start, cancellation)?,
+                    });
+                }
+
+                let unix_target = uds::parse_unix_url(&url)
+                    .or_else(|| unix_socket_opt.clone().map(|socket_path| (socket_path, url.clone())));
 
-                if let Some(headers) = headers {
-                    for (key, value) in headers?.iter() {
+                if let Some((socket_path, request_path)) = unix_target {
+                    let request_uri: Uri = request_path.parse().or_throw(&ctx)?;
+                    let host = url.parse::<Uri>().ok().and_then(|u| u.authority().map(|a| a.to_string()));
+
+                    let mut req = Request::builder()
+                        .method(method.clone())
+                        .uri(request_uri)
+                        .header("user-agent", format!("llrt {}", VERSION))
+                        .header("accept", "*/*")
+                        .header("host", host.as_deref().unwrap_or("localhost"));
+
+                    for (key, value) in headers.iter() {
                         req = req.header(key, value)
                     }
+
+                    let req = req.body(Body::from(body_bytes)).or_throw(&ctx)?;
+                    let res = cancellation
+                        .guard(&ctx, uds::send(&ctx, &socket_path, req))
+                        .await?;
+
+                    return Ok(Response {
+                        data: ResponseData::new(ctx, res, method_string, url, false, start, cancellation)?,
+                    });
                 }
 
-                let body = body?;
+                let mut uri: Uri = url.parse().or_throw(&ctx)?;
+                let mut redirected = false;
+                let mut redirect_count = 0u8;
+                let mut hop_body = body_bytes;
+
+                let res = loop {
+                    ensure_url_access(&ctx, &uri)?;
+
+                    let mut req = Request::builder()
+                        .method(method.clone())
+                        .uri(uri.clone())
+                        .header("user-agent", format!("llrt {}", VERSION))
+                        .header("accept", "*/*");
+
+                    if headers.get("accept-encoding").is_none() {
+                        req = req.header("accept-encoding", "gzip, deflate, br");
+                    }
+
+                    for (key, value) in headers.iter() {
+                        req = req.header(key, value)
+                    }
+
+                    let req = req.body(Body::from(hop_body.clone())).or_throw(&ctx)?;
+
+                    let res = cancellation
+                        .guard(&ctx, async { client.request(req).await.or_throw(&ctx) })
+                        .await?; //TODO return ErrorObject
+
+                    let status = res.status().as_u16();
+                    let is_redirect = matches!(status, 301 | 302 | 303 | 307 | 308);
+
+                    if !is_redirect {
+                        break res;
+                    }
+
+                    if redirect == RedirectMode::Manual {
+                        break res;
+                    }
+
+                    if redirect == RedirectMode::Error {
+                        return Err(Exception::throw_type(
+                            &ctx,
+                            &format!("Redirect from {} not allowed by redirect mode \"error\"", uri),
+                        ));
+                    }
+
+                    let location = match res
+                        .headers()
+                        .get(hyper::header::LOCATION)
+                        .and_then(|v| v.to_str().ok())
+                    {
+                        Some(location) => location.to_string(),
+                        None => break res,
+                    };
+
+                    let next_uri = resolve_location(&uri, &location).ok_or_else(|| {
+                        Exception::throw_type(&ctx, &format!("Invalid redirect location: {}", location))
+                    })?;
+
+                    if !is_same_origin(&uri, &next_uri) {
+                        headers.remove("authorization");
+                        headers.remove("cookie");
+                        headers.remove("proxy-authorization");
+                    }
+
+                    if status == 303 || ((status == 301 || status == 302) && method == hyper::Method::POST) {
+                        method = hyper::Method::GET;
+                        hop_body.clear();
+                        headers.remove("content-length");
+                        headers.remove("content-type");
+                    }
+
+                    if redirect_count >= MAX_REDIRECTS {
+                        return Err(Exception::throw_type(&ctx, "Too many redirects"));
+                    }
+                    redirect_count += 1;
+
+                    redirected = true;
+                    uri = next_uri;
+                };
 
-                let req = req.body(body).or_throw(&ctx)?;
-                let res = client.request(req).await.or_throw(&ctx)?; //TODO return ErrorObject
+                let final_url = uri.to_string();
 
-                Ok::<Response, Error>(Response {
-                    data: ResponseData::new(ctx, res, method_string, url, start)?,
+                Ok::<Response<'_>, Error>(Response {
+                    data: ResponseData::new(ctx, res, method_string, final_url, redirected, start, cancellation)?,
                 })
             }
         })),
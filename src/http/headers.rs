@@ -0,0 +1,54 @@
+use hyper::HeaderMap;
+use rquickjs::{Ctx, Result, Value};
+
+use crate::util::ResultExt;
+
+#[derive(Clone, Default)]
+pub struct Headers(Vec<(String, String)>);
+
+impl Headers {
+    pub fn from_value(ctx: Ctx<'_>, value: Value<'_>) -> Result<Self> {
+        if let Some(obj) = value.as_object() {
+            let mut headers = Vec::with_capacity(obj.len());
+            for prop in obj.props::<String, String>() {
+                let (key, value) = prop?;
+                headers.push((key.to_lowercase(), value));
+            }
+            return Ok(Self(headers));
+        }
+        Err(ctx.throw(rquickjs::Exception::throw_type(&ctx, "Invalid headers value")))
+    }
+
+    pub fn from_http_header_map(map: HeaderMap) -> Result<Self> {
+        let mut headers = Vec::with_capacity(map.len());
+        for (key, value) in map.iter() {
+            headers.push((
+                key.as_str().to_lowercase(),
+                value.to_str().unwrap_or_default().to_string(),
+            ));
+        }
+        Ok(Self(headers))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        let name = name.to_lowercase();
+        self.0
+            .iter()
+            .find(|(k, _)| *k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        let name = name.to_lowercase();
+        self.0.retain(|(k, _)| *k != name);
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) {
+        self.remove(name);
+        self.0.push((name.to_lowercase(), value.to_string()));
+    }
+}
@@ -0,0 +1,6 @@
+pub(crate) mod data_url;
+pub mod fetch;
+pub mod headers;
+pub mod proxy;
+pub mod response;
+pub(crate) mod uds;
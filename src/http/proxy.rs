@@ -0,0 +1,336 @@
+use std::{
+    env,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hyper::{client::connect::Connection, service::Service, Uri};
+use hyper_rustls::HttpsConnector;
+use rustls::ServerName;
+use tokio::{
+    io::{self, AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf},
+    net::TcpStream,
+};
+use tokio_rustls::{client::TlsStream, TlsConnector};
+
+#[derive(Clone)]
+struct ProxyConfig {
+    uri: Uri,
+    authorization: Option<String>,
+}
+
+impl ProxyConfig {
+    fn from_env(var: &str) -> Option<Self> {
+        let value = env::var(var)
+            .or_else(|_| env::var(var.to_lowercase()))
+            .ok()?;
+        let uri: Uri = value.parse().ok()?;
+
+        let authorization = uri.authority().and_then(|authority| {
+            let authority = authority.as_str();
+            let (userinfo, _) = authority.split_once('@')?;
+            Some(format!("Basic {}", BASE64.encode(userinfo)))
+        });
+
+        Some(Self { uri, authorization })
+    }
+}
+
+#[derive(Clone, Default)]
+struct NoProxy(Vec<String>);
+
+impl NoProxy {
+    fn from_env() -> Self {
+        let value = env::var("NO_PROXY")
+            .or_else(|_| env::var("no_proxy"))
+            .unwrap_or_default();
+        Self(
+            value
+                .split(',')
+                .map(|s| s.trim().trim_start_matches('.').to_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect(),
+        )
+    }
+
+    fn matches(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        self.0
+            .iter()
+            .any(|suffix| suffix == "*" || host == *suffix || host.ends_with(&format!(".{}", suffix)))
+    }
+}
+
+/// Wraps the TLS-capable connector with proxy awareness driven by
+/// `HTTP_PROXY`/`HTTPS_PROXY`/`NO_PROXY`.
+#[derive(Clone)]
+pub struct ProxyConnector {
+    inner: HttpsConnector<hyper::client::HttpConnector>,
+    tls: TlsConnector,
+    http_proxy: Option<ProxyConfig>,
+    https_proxy: Option<ProxyConfig>,
+    no_proxy: NoProxy,
+}
+
+impl ProxyConnector {
+    pub fn new(
+        inner: HttpsConnector<hyper::client::HttpConnector>,
+        tls_config: rustls::ClientConfig,
+    ) -> Self {
+        Self {
+            inner,
+            tls: TlsConnector::from(std::sync::Arc::new(tls_config)),
+            http_proxy: ProxyConfig::from_env("HTTP_PROXY"),
+            https_proxy: ProxyConfig::from_env("HTTPS_PROXY"),
+            no_proxy: NoProxy::from_env(),
+        }
+    }
+
+    fn proxy_for(&self, dst: &Uri) -> Option<&ProxyConfig> {
+        if let Some(host) = dst.host() {
+            if self.no_proxy.matches(host) {
+                return None;
+            }
+        }
+
+        match dst.scheme_str() {
+            Some("https") => self.https_proxy.as_ref(),
+            _ => self.http_proxy.as_ref(),
+        }
+    }
+}
+
+pub enum ProxyStream {
+    Direct(<HttpsConnector<hyper::client::HttpConnector> as Service<Uri>>::Response),
+    Tunneled(TlsStream<PrefixedStream<TcpStream>>),
+    TunneledPlain(TcpStream),
+}
+
+/// Wraps a stream with bytes already read off the wire (e.g. the tail of a
+/// proxy's CONNECT response that arrived in the same read as the start of
+/// the origin's TLS handshake), so they're replayed to the first reader
+/// instead of being dropped on the floor.
+pub struct PrefixedStream<S> {
+    prefix: Vec<u8>,
+    prefix_pos: usize,
+    inner: S,
+}
+
+impl<S> PrefixedStream<S> {
+    fn new(prefix: Vec<u8>, inner: S) -> Self {
+        Self {
+            prefix,
+            prefix_pos: 0,
+            inner,
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for PrefixedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.prefix_pos < this.prefix.len() {
+            let remaining = &this.prefix[this.prefix_pos..];
+            let n = remaining.len().min(buf.remaining());
+            buf.put_slice(&remaining[..n]);
+            this.prefix_pos += n;
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for PrefixedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+impl Connection for ProxyStream {
+    fn connected(&self) -> hyper::client::connect::Connected {
+        match self {
+            Self::Direct(stream) => stream.connected(),
+            Self::Tunneled(_) => hyper::client::connect::Connected::new(),
+            // Plain-HTTP forwarding keeps sending absolute-form request
+            // lines for the rest of the connection, so hyper must be told
+            // this is a proxied connection or it reverts to origin-form.
+            Self::TunneledPlain(_) => hyper::client::connect::Connected::new().proxy(true),
+        }
+    }
+}
+
+impl AsyncRead for ProxyStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Direct(stream) => Pin::new(stream).poll_read(cx, buf),
+            Self::Tunneled(stream) => Pin::new(stream).poll_read(cx, buf),
+            Self::TunneledPlain(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for ProxyStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Direct(stream) => Pin::new(stream).poll_write(cx, buf),
+            Self::Tunneled(stream) => Pin::new(stream).poll_write(cx, buf),
+            Self::TunneledPlain(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Direct(stream) => Pin::new(stream).poll_flush(cx),
+            Self::Tunneled(stream) => Pin::new(stream).poll_flush(cx),
+            Self::TunneledPlain(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Direct(stream) => Pin::new(stream).poll_shutdown(cx),
+            Self::Tunneled(stream) => Pin::new(stream).poll_shutdown(cx),
+            Self::TunneledPlain(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+async fn connect_proxy(proxy: &ProxyConfig) -> io::Result<TcpStream> {
+    let proxy_host = proxy.uri.host().unwrap_or_default();
+    let proxy_port = proxy
+        .uri
+        .port_u16()
+        .unwrap_or(if proxy.uri.scheme_str() == Some("https") { 443 } else { 80 });
+
+    TcpStream::connect((proxy_host, proxy_port)).await
+}
+
+const MAX_CONNECT_RESPONSE_LEN: usize = 8192;
+
+/// Issues a `CONNECT` through an already-established proxy connection so
+/// a subsequent TLS handshake can terminate end-to-end at `dst`. Reads
+/// incrementally until the header terminator, since the status line can
+/// arrive split across multiple reads, and hands back any bytes read past
+/// it instead of discarding them — a proxy may coalesce the "200
+/// Connection established" reply with the start of the origin's TLS
+/// ServerHello in the same packet.
+async fn tunnel(
+    mut stream: TcpStream,
+    proxy: &ProxyConfig,
+    dst: &Uri,
+) -> io::Result<PrefixedStream<TcpStream>> {
+    let host = dst.host().unwrap_or_default();
+    let port = dst.port_u16().unwrap_or(443);
+
+    let mut connect_req = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+    if let Some(authorization) = &proxy.authorization {
+        connect_req.push_str(&format!("Proxy-Authorization: {authorization}\r\n"));
+    }
+    connect_req.push_str("\r\n");
+
+    stream.write_all(connect_req.as_bytes()).await?;
+
+    let mut buf = Vec::new();
+    let header_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buf.len() > MAX_CONNECT_RESPONSE_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "proxy CONNECT response too large",
+            ));
+        }
+
+        let mut chunk = [0u8; 1024];
+        let n = tokio::io::AsyncReadExt::read(&mut stream, &mut chunk).await?;
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "proxy closed the connection during CONNECT",
+            ));
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let status_line = String::from_utf8_lossy(&buf[..header_end]);
+    if !status_line.starts_with("HTTP/1.1 200") && !status_line.starts_with("HTTP/1.0 200") {
+        return Err(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            format!(
+                "proxy CONNECT failed: {}",
+                status_line.lines().next().unwrap_or_default()
+            ),
+        ));
+    }
+
+    let leftover = buf[header_end..].to_vec();
+    Ok(PrefixedStream::new(leftover, stream))
+}
+
+impl Service<Uri> for ProxyConnector {
+    type Response = ProxyStream;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Service::<Uri>::poll_ready(&mut self.inner, cx).map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let proxy = self.proxy_for(&dst).cloned();
+        let is_https = dst.scheme_str() == Some("https");
+        let tls = self.tls.clone();
+        let mut inner = self.inner.clone();
+
+        Box::pin(async move {
+            let Some(proxy) = proxy else {
+                return Service::<Uri>::call(&mut inner, dst)
+                    .await
+                    .map(ProxyStream::Direct)
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e));
+            };
+
+            if is_https {
+                let host = dst.host().unwrap_or_default().to_string();
+                let tcp = connect_proxy(&proxy).await?;
+                let tcp = tunnel(tcp, &proxy, &dst).await?;
+                let server_name = ServerName::try_from(host.as_str())
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                let tls_stream = tls.connect(server_name, tcp).await?;
+                Ok(ProxyStream::Tunneled(tls_stream))
+            } else {
+                // Plain HTTP is simply forwarded over the proxy connection;
+                // the proxy reads the absolute-form request line itself.
+                let tcp = connect_proxy(&proxy).await?;
+                Ok(ProxyStream::TunneledPlain(tcp))
+            }
+        })
+    }
+}
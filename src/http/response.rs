@@ -0,0 +1,237 @@
+use std::io::Read;
+use std::sync::Arc;
+use std::time::Instant;
+
+use brotli::Decompressor as BrotliDecoder;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use hyper::{body, Body, Response as HyperResponse, StatusCode};
+use rquickjs::{prelude::This, ArrayBuffer, Ctx, Exception, Function, Object, Result, TypedArray};
+use tokio::sync::Notify;
+
+use crate::{http::headers::Headers, util::ResultExt};
+
+/// Resolves once the given signal's `abort` event fires; stays pending
+/// forever if no signal was registered.
+async fn wait_for_abort(notify: Option<&Notify>) {
+    match notify {
+        Some(notify) => notify.notified().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Resolves once the deadline elapses; stays pending forever if no
+/// timeout was configured.
+async fn wait_for_timeout(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Pairs the `Notify` a `fetch()` call waits on with the `addEventListener`
+/// registration that feeds it, so the listener is removed as soon as this
+/// is dropped instead of accumulating on a signal reused across requests.
+pub struct AbortState<'js> {
+    notify: Arc<Notify>,
+    signal: Object<'js>,
+    listener: Function<'js>,
+}
+
+impl<'js> AbortState<'js> {
+    pub fn new(notify: Arc<Notify>, signal: Object<'js>, listener: Function<'js>) -> Self {
+        Self {
+            notify,
+            signal,
+            listener,
+        }
+    }
+}
+
+impl<'js> Drop for AbortState<'js> {
+    fn drop(&mut self) {
+        if let Ok(remove_event_listener) = self.signal.get::<_, Function>("removeEventListener") {
+            let _ = remove_event_listener
+                .call::<_, ()>((This(self.signal.clone()), "abort", self.listener.clone()));
+        }
+    }
+}
+
+/// Shared abort/timeout state threaded from the initial `fetch()` call
+/// through to every later body read, so aborting or timing out mid-body
+/// read stops delivering data instead of only guarding the initial
+/// connect.
+#[derive(Clone, Default)]
+pub struct Cancellation<'js> {
+    pub abort: Option<Arc<AbortState<'js>>>,
+    pub deadline: Option<tokio::time::Instant>,
+}
+
+impl<'js> Cancellation<'js> {
+    pub async fn guard<T>(
+        &self,
+        ctx: &Ctx<'_>,
+        fut: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        tokio::select! {
+            biased;
+            _ = wait_for_abort(self.abort.as_deref().map(|state| state.notify.as_ref())) => {
+                Err(Exception::throw_message(ctx, "The operation was aborted"))
+            }
+            _ = wait_for_timeout(self.deadline) => {
+                Err(Exception::throw_type(ctx, "The operation timed out"))
+            }
+            result = fut => result,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ContentEncoding {
+    Gzip,
+    Deflate,
+    Brotli,
+}
+
+impl ContentEncoding {
+    fn from_header(value: &str) -> Option<Self> {
+        match value.trim() {
+            "gzip" | "x-gzip" => Some(Self::Gzip),
+            "deflate" => Some(Self::Deflate),
+            "br" => Some(Self::Brotli),
+            _ => None,
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        match self {
+            Self::Gzip => GzDecoder::new(bytes).read_to_end(&mut decoded)?,
+            Self::Deflate => ZlibDecoder::new(bytes).read_to_end(&mut decoded)?,
+            Self::Brotli => BrotliDecoder::new(bytes, 4096).read_to_end(&mut decoded)?,
+        };
+        Ok(decoded)
+    }
+}
+
+pub struct ResponseData<'js> {
+    pub method: String,
+    pub url: String,
+    pub redirected: bool,
+    pub status: StatusCode,
+    pub headers: Headers,
+    pub http_version: String,
+    pub start: Instant,
+    body: Body,
+    content_encoding: Option<ContentEncoding>,
+    cancellation: Cancellation<'js>,
+}
+
+fn format_version(version: hyper::Version) -> String {
+    match version {
+        hyper::Version::HTTP_09 => "HTTP/0.9",
+        hyper::Version::HTTP_10 => "HTTP/1.0",
+        hyper::Version::HTTP_11 => "HTTP/1.1",
+        hyper::Version::HTTP_2 => "HTTP/2.0",
+        hyper::Version::HTTP_3 => "HTTP/3.0",
+        _ => "HTTP/1.1",
+    }
+    .to_string()
+}
+
+impl<'js> ResponseData<'js> {
+    pub fn new(
+        _ctx: Ctx<'js>,
+        response: HyperResponse<Body>,
+        method: String,
+        url: String,
+        redirected: bool,
+        start: Instant,
+        cancellation: Cancellation<'js>,
+    ) -> Result<Self> {
+        let status = response.status();
+        let http_version = format_version(response.version());
+        let (parts, body) = response.into_parts();
+        let mut headers = Headers::from_http_header_map(parts.headers)?;
+
+        let content_encoding = headers
+            .get("content-encoding")
+            .and_then(ContentEncoding::from_header);
+
+        if content_encoding.is_some() {
+            headers.remove("content-encoding");
+            headers.remove("content-length");
+        }
+
+        Ok(Self {
+            method,
+            url,
+            redirected,
+            status,
+            headers,
+            http_version,
+            start,
+            body,
+            content_encoding,
+            cancellation,
+        })
+    }
+
+    async fn bytes(&mut self, ctx: &Ctx<'_>) -> Result<Vec<u8>> {
+        let body = std::mem::replace(&mut self.body, Body::empty());
+        let cancellation = self.cancellation.clone();
+        let bytes = cancellation
+            .guard(ctx, async { body::to_bytes(body).await.or_throw(ctx) })
+            .await?;
+
+        match self.content_encoding {
+            Some(encoding) => encoding.decode(&bytes).or_throw(ctx),
+            None => Ok(bytes.to_vec()),
+        }
+    }
+}
+
+pub struct Response<'js> {
+    pub data: ResponseData<'js>,
+}
+
+impl<'js> Response<'js> {
+    pub async fn text(&mut self, ctx: Ctx<'_>) -> Result<String> {
+        let bytes = self.data.bytes(&ctx).await?;
+        String::from_utf8(bytes).or_throw(&ctx)
+    }
+
+    pub async fn json(&mut self, ctx: Ctx<'_>) -> Result<Object<'_>> {
+        let text = self.text(ctx.clone()).await?;
+        ctx.json_parse(text)
+    }
+
+    pub async fn array_buffer<'c>(&mut self, ctx: Ctx<'c>) -> Result<ArrayBuffer<'c>> {
+        let bytes = self.data.bytes(&ctx).await?;
+        ArrayBuffer::new(ctx, bytes)
+    }
+
+    pub async fn bytes<'c>(&mut self, ctx: Ctx<'c>) -> Result<TypedArray<'c, u8>> {
+        let bytes = self.data.bytes(&ctx).await?;
+        TypedArray::new(ctx, bytes)
+    }
+
+    pub fn status(&self) -> u16 {
+        self.data.status.as_u16()
+    }
+
+    pub fn ok(&self) -> bool {
+        self.data.status.is_success()
+    }
+
+    pub fn url(&self) -> String {
+        self.data.url.clone()
+    }
+
+    pub fn redirected(&self) -> bool {
+        self.data.redirected
+    }
+
+    pub fn http_version(&self) -> String {
+        self.data.http_version.clone()
+    }
+}
@@ -0,0 +1,40 @@
+use hyper::{client::conn::Builder, Body, Request, Response};
+use rquickjs::{Ctx, Result};
+use tokio::net::UnixStream;
+
+use crate::{security::ensure_unix_socket_access, util::ResultExt};
+
+/// Parses the `unix:<socket-path>//<request-path>` resource form into its
+/// socket path and request path components.
+pub(crate) fn parse_unix_url(url: &str) -> Option<(String, String)> {
+    let rest = url.strip_prefix("unix:")?;
+    let (socket_path, request_path) = rest.split_once("//").unwrap_or((rest, ""));
+    let request_path = if request_path.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{request_path}")
+    };
+    Some((socket_path.to_string(), request_path))
+}
+
+/// Sends a single request over a Unix domain socket. There's no TLS and no
+/// host to run through the `LLRT_NET_ALLOW`/`LLRT_NET_DENY` checks, but a
+/// socket path is just as capable of reaching something sensitive as a
+/// blocked host would be, so it must be explicitly listed in
+/// `LLRT_NET_ALLOW_UNIX` before it's dialed.
+pub(crate) async fn send(
+    ctx: &Ctx<'_>,
+    socket_path: &str,
+    req: Request<Body>,
+) -> Result<Response<Body>> {
+    ensure_unix_socket_access(ctx, socket_path)?;
+
+    let stream = UnixStream::connect(socket_path).await.or_throw(ctx)?;
+    let (mut sender, connection) = Builder::new().handshake(stream).await.or_throw(ctx)?;
+
+    tokio::spawn(async move {
+        let _ = connection.await;
+    });
+
+    sender.send_request(req).await.or_throw(ctx)
+}
@@ -0,0 +1,5 @@
+pub mod http;
+pub mod security;
+pub mod util;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
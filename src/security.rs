@@ -0,0 +1,88 @@
+use hyper::Uri;
+use once_cell::sync::Lazy;
+use rquickjs::{Ctx, Exception, Result};
+use std::env;
+
+fn parse_uri_list(var: &str) -> Option<std::result::Result<Vec<Uri>, hyper::http::uri::InvalidUri>> {
+    let value = env::var(var).ok()?;
+    Some(
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<Uri>())
+            .collect(),
+    )
+}
+
+pub static HTTP_ALLOW_LIST: Lazy<Option<std::result::Result<Vec<Uri>, hyper::http::uri::InvalidUri>>> =
+    Lazy::new(|| parse_uri_list("LLRT_NET_ALLOW"));
+
+pub static HTTP_DENY_LIST: Lazy<Option<std::result::Result<Vec<Uri>, hyper::http::uri::InvalidUri>>> =
+    Lazy::new(|| parse_uri_list("LLRT_NET_DENY"));
+
+fn host_matches(allowed: &Uri, host: &str) -> bool {
+    allowed.host().map(|h| h.eq_ignore_ascii_case(host)).unwrap_or(false)
+}
+
+fn parse_path_list(var: &str) -> Option<Vec<String>> {
+    let value = env::var(var).ok()?;
+    Some(
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+pub static UNIX_SOCKET_ALLOW_LIST: Lazy<Option<Vec<String>>> =
+    Lazy::new(|| parse_path_list("LLRT_NET_ALLOW_UNIX"));
+
+/// Unix sockets skip host-based allow/deny entirely (there's no host to
+/// check), so access is deny-by-default: a script can otherwise reach any
+/// local socket the process can, e.g. `fetch(url, {socketPath:
+/// "/var/run/docker.sock"})`. Require each path to be explicitly listed in
+/// `LLRT_NET_ALLOW_UNIX` before it's dialed.
+pub fn ensure_unix_socket_access(ctx: &Ctx<'_>, socket_path: &str) -> Result<()> {
+    let allowed = matches!(
+        &*UNIX_SOCKET_ALLOW_LIST,
+        Some(allow_list) if allow_list.iter().any(|allowed| allowed == socket_path)
+    );
+
+    if !allowed {
+        return Err(Exception::throw_type(
+            ctx,
+            &format!(
+                "Unix socket access to \"{socket_path}\" not allowed; add it to \"LLRT_NET_ALLOW_UNIX\" to permit it"
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn ensure_url_access(ctx: &Ctx<'_>, uri: &Uri) -> Result<()> {
+    let host = uri.host().unwrap_or_default();
+
+    if let Some(Ok(deny_list)) = &*HTTP_DENY_LIST {
+        if deny_list.iter().any(|denied| host_matches(denied, host)) {
+            return Err(Exception::throw_type(
+                ctx,
+                &format!("URL access denied by \"LLRT_NET_DENY\": {}", uri),
+            ));
+        }
+    }
+
+    if let Some(Ok(allow_list)) = &*HTTP_ALLOW_LIST {
+        if !allow_list.iter().any(|allowed| host_matches(allowed, host)) {
+            return Err(Exception::throw_type(
+                ctx,
+                &format!("URL access not allowed by \"LLRT_NET_ALLOW\": {}", uri),
+            ));
+        }
+    }
+
+    Ok(())
+}
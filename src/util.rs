@@ -0,0 +1,52 @@
+use rquickjs::{Ctx, Object, Result, Value};
+
+pub trait ResultExt<T> {
+    fn or_throw(self, ctx: &Ctx<'_>) -> Result<T>;
+    fn or_throw_msg(self, ctx: &Ctx<'_>, message: &str) -> Result<T>;
+}
+
+impl<T, E: std::fmt::Display> ResultExt<T> for std::result::Result<T, E> {
+    fn or_throw(self, ctx: &Ctx<'_>) -> Result<T> {
+        self.map_err(|err| {
+            rquickjs::Exception::throw_type(ctx, &err.to_string())
+        })
+    }
+
+    fn or_throw_msg(self, ctx: &Ctx<'_>, message: &str) -> Result<T> {
+        self.map_err(|err| {
+            rquickjs::Exception::throw_type(ctx, &format!("{message}: {err}"))
+        })
+    }
+}
+
+pub trait ObjectExt<'js> {
+    fn get_optional<K: rquickjs::IntoJs<'js> + AsRef<str>, V: rquickjs::FromJs<'js>>(
+        &self,
+        key: K,
+    ) -> Result<Option<V>>;
+}
+
+impl<'js> ObjectExt<'js> for Object<'js> {
+    fn get_optional<K: rquickjs::IntoJs<'js> + AsRef<str>, V: rquickjs::FromJs<'js>>(
+        &self,
+        key: K,
+    ) -> Result<Option<V>> {
+        if self.contains_key(key.as_ref())? {
+            Ok(Some(self.get(key)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+pub fn get_bytes(ctx: &Ctx<'_>, value: Value<'_>) -> Result<Vec<u8>> {
+    if let Some(s) = value.as_string() {
+        return Ok(s.to_string()?.into_bytes());
+    }
+    if let Ok(buf) = rquickjs::ArrayBuffer::from_value(value.clone()) {
+        if let Some(bytes) = buf.as_bytes() {
+            return Ok(bytes.to_vec());
+        }
+    }
+    Err(rquickjs::Exception::throw_type(ctx, "Unsupported body type"))
+}